@@ -11,6 +11,7 @@ use std::env;
 use std::fs::File;
 use std::io::{ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 use wisent::error::ParseError;
 use wisent::grammar::Grammar;
 use wisent::lexer::Dfa;
@@ -22,8 +23,16 @@ struct GrammarDownload {
     extensions: Vec<String>,
 }
 
+/// Metadata sitting next to a vendored `*.g4` file (same stem, `.toml`
+/// extension) describing which file extensions the grammar should handle.
+#[derive(Deserialize)]
+struct GrammarMeta {
+    extensions: Vec<String>,
+}
+
 fn main() -> Result<(), BuildScriptError> {
     println!("cargo:rerun-if-changed=grammars.toml");
+    println!("cargo:rerun-if-changed=grammars");
     let outdir = env::var_os("OUT_DIR")
         .unwrap()
         .to_str()
@@ -34,29 +43,81 @@ fn main() -> Result<(), BuildScriptError> {
     let output_rust_file = Path::new(&outdir).join("assign_grammars.in");
     std::fs::create_dir_all(&downloaded_dir)?;
     std::fs::create_dir_all(&generated_dir)?;
-    let dfa_map = download_and_generate_grammars("grammars.toml", &downloaded_dir, &generated_dir)?;
-    print_grammar_assignment(dfa_map, output_rust_file)?;
+    // Vendored grammars in `grammars/` take precedence and let the crate build
+    // with no connectivity; only reach for the network for what is not vendored.
+    let mut generated = generate_vendored_grammars("grammars", &generated_dir)?;
+    if Path::new("grammars.toml").exists() {
+        let downloaded = download_and_generate_grammars(
+            "grammars.toml",
+            &downloaded_dir,
+            &generated_dir,
+            &generated.dfa_map,
+        )?;
+        generated.merge(downloaded);
+    }
+    print_grammar_assignment(generated, output_rust_file)?;
     Ok(())
 }
 
+/// The outcome of compiling the grammar list: the extension-to-DFA mapping plus
+/// the grammar-level and per-rule doc comments collected along the way, which
+/// are re-emitted as `///` lines on the generated code.
+struct GrammarAssignment {
+    dfa_map: HashMap<String, String>,
+    docs: Vec<String>,
+    rule_docs: Vec<(String, String)>,
+}
+
+impl GrammarAssignment {
+    /// Folds `other` into `self` without overriding extensions that are already
+    /// mapped, so that vendored grammars win over downloaded ones.
+    fn merge(&mut self, other: GrammarAssignment) {
+        for (extension, dfa) in other.dfa_map {
+            self.dfa_map.entry(extension).or_insert(dfa);
+        }
+        self.docs.extend(other.docs);
+        self.rule_docs.extend(other.rule_docs);
+    }
+}
+
 /// Prints a file matching the extensions to the generated grammar
 fn print_grammar_assignment<P: AsRef<Path>>(
-    dfa_map: HashMap<String, String>,
+    generated: GrammarAssignment,
     output: P,
 ) -> Result<(), BuildScriptError> {
+    let GrammarAssignment {
+        dfa_map,
+        docs,
+        rule_docs,
+    } = generated;
     let mut f = File::create(output)?;
+    // Surface the grammar-level doc comments extracted from the source grammars.
+    for doc in &docs {
+        for line in doc.lines() {
+            writeln!(f, "/// {}", line)?;
+        }
+    }
+    // Surface each rule's own doc comment, one bullet per rule.
+    if !rule_docs.is_empty() {
+        writeln!(f, "///")?;
+        writeln!(f, "/// # Rules")?;
+        for (rule, doc) in &rule_docs {
+            let doc = doc.lines().collect::<Vec<_>>().join(" ");
+            writeln!(f, "/// - `{}`: {}", rule, doc)?;
+        }
+    }
     writeln!(
         f,
-        "/// Returns a vector of bytes containing the lexer DFA implementation, given"
+        "/// Returns the serialized lexer DFA for the given file extension, or an"
     )?;
-    writeln!(f, "/// the file extension.")?;
-    writeln!(f, "fn assign_dfas(extension: &str) {{")?;
+    writeln!(f, "/// empty slice when the extension is unknown.")?;
+    writeln!(f, "fn assign_dfas(extension: &str) -> &'static [u8] {{")?;
     writeln!(f, "    match extension {{")?;
     for (extension, dfa_bytes) in dfa_map.into_iter() {
         if extension.chars().all(|x| x.is_alphanumeric()) {
             writeln!(
                 f,
-                "        \"{}\" => include_bytes!(\"{}\"),",
+                "        \"{}\" => include_bytes!(\"{}\").as_slice(),",
                 extension, dfa_bytes
             )?;
         } else {
@@ -66,7 +127,7 @@ fn print_grammar_assignment<P: AsRef<Path>>(
             ))?
         }
     }
-    writeln!(f, "        _ => Vec::new(),")?;
+    writeln!(f, "        _ => &[],")?;
     write!(f, "    }}\n}}\n")?;
     Ok(())
 }
@@ -78,11 +139,19 @@ fn download_and_generate_grammars<P: AsRef<Path>>(
     list: &str,
     downloaded_dir: P,
     generated_dir: P,
-) -> Result<HashMap<String, String>, BuildScriptError> {
+    vendored: &HashMap<String, String>,
+) -> Result<GrammarAssignment, BuildScriptError> {
     let list_content = std::fs::read_to_string(list)?;
     let toml: HashMap<String, GrammarDownload> = toml::from_str(&list_content)?;
     let mut parsers = HashMap::new();
+    let mut docs = Vec::new();
+    let mut rule_docs = Vec::new();
     for (key, value) in toml {
+        // Every extension this grammar handles is already vendored locally;
+        // skip the network entirely instead of downloading a redundant copy.
+        if value.extensions.iter().all(|ext| vendored.contains_key(ext)) {
+            continue;
+        }
         let downloaded_langdir = PathBuf::from(downloaded_dir.as_ref()).join(&key);
         let generated_langdir = PathBuf::from(generated_dir.as_ref()).join(&key);
         std::fs::create_dir_all(&generated_langdir)?;
@@ -92,7 +161,22 @@ fn download_and_generate_grammars<P: AsRef<Path>>(
         let downloaded_file = downloaded_langdir.join(filename);
         let generated_file = generated_langdir.join(format!("{}.dfa", filestem));
         let grammar = Grammar::parse_grammar(downloaded_file.as_path().to_str().unwrap())?;
-        let dfa = Dfa::new(&grammar);
+        // A grammar may pull in additional files through `import` statements; rerun
+        // the build whenever any of the files that contributed to it changes.
+        for source in grammar.sources() {
+            println!("cargo:rerun-if-changed={}", source.display());
+        }
+        if let Some(doc) = grammar.doc() {
+            docs.push(doc.to_string());
+        }
+        rule_docs.extend(
+            grammar
+                .rule_docs()
+                .iter()
+                .map(|(rule, doc)| (rule.clone(), doc.clone())),
+        );
+        // Minimize before serialization to shrink the embedded DFA tables.
+        let dfa = Dfa::new(&grammar).minimize();
         let encoded_dfa = dfa.as_bytes();
         std::fs::write(generated_file.as_path(), encoded_dfa)?;
         for extension in value.extensions {
@@ -102,7 +186,76 @@ fn download_and_generate_grammars<P: AsRef<Path>>(
             );
         }
     }
-    Ok(parsers)
+    Ok(GrammarAssignment {
+        dfa_map: parsers,
+        docs,
+        rule_docs,
+    })
+}
+
+/// Walks a local `grammars/` directory, compiling every `*.g4` file it finds
+/// into a DFA without touching the network. The language key is derived from
+/// the path of the grammar relative to `dir` (directory components joined with
+/// `/`, falling back to the file stem), and the handled file extensions are
+/// read from an adjacent `<stem>.toml` metadata file.
+fn generate_vendored_grammars<P: AsRef<Path>>(
+    dir: &str,
+    generated_dir: P,
+) -> Result<GrammarAssignment, BuildScriptError> {
+    let mut parsers = HashMap::new();
+    let mut docs = Vec::new();
+    let mut rule_docs = Vec::new();
+    let root = Path::new(dir);
+    if !root.exists() {
+        return Ok(GrammarAssignment {
+            dfa_map: parsers,
+            docs,
+            rule_docs,
+        });
+    }
+    for entry in WalkDir::new(root).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("g4") {
+            continue;
+        }
+        let filestem = path.file_stem().unwrap().to_str().unwrap();
+        let key = match path.parent().and_then(|p| p.strip_prefix(root).ok()) {
+            Some(rel) if !rel.as_os_str().is_empty() => rel.to_str().unwrap().replace('\\', "/"),
+            _ => filestem.to_string(),
+        };
+        let meta_path = path.with_extension("toml");
+        let meta: GrammarMeta = toml::from_str(&std::fs::read_to_string(&meta_path)?)?;
+        let generated_langdir = PathBuf::from(generated_dir.as_ref()).join(&key);
+        std::fs::create_dir_all(&generated_langdir)?;
+        let generated_file = generated_langdir.join(format!("{}.dfa", filestem));
+        let grammar = Grammar::parse_grammar(path.to_str().unwrap())?;
+        for source in grammar.sources() {
+            println!("cargo:rerun-if-changed={}", source.display());
+        }
+        if let Some(doc) = grammar.doc() {
+            docs.push(doc.to_string());
+        }
+        rule_docs.extend(
+            grammar
+                .rule_docs()
+                .iter()
+                .map(|(rule, doc)| (rule.clone(), doc.clone())),
+        );
+        // Minimize before serialization to shrink the embedded DFA tables.
+        let dfa = Dfa::new(&grammar).minimize();
+        std::fs::write(generated_file.as_path(), dfa.as_bytes())?;
+        for extension in meta.extensions {
+            parsers.insert(
+                extension,
+                generated_file.as_path().to_str().unwrap().to_string(),
+            );
+        }
+    }
+    Ok(GrammarAssignment {
+        dfa_map: parsers,
+        docs,
+        rule_docs,
+    })
 }
 
 /// Downloads a file from the web, and asserts the sha256 is the expected one.
@@ -161,6 +314,18 @@ enum BuildScriptError {
     Io(std::io::Error),
 }
 
+impl std::fmt::Display for BuildScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildScriptError::Parse(err) => write!(f, "grammar parse error: {}", err),
+            BuildScriptError::Toml(err) => write!(f, "grammars.toml error: {}", err),
+            BuildScriptError::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for BuildScriptError {}
+
 impl From<ParseError> for BuildScriptError {
     fn from(err: ParseError) -> Self {
         BuildScriptError::Parse(err)
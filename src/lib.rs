@@ -0,0 +1,21 @@
+//! Compile-time generated lexer DFAs.
+//!
+//! The build script compiles every configured grammar into a serialized
+//! [`wisent::lexer::Dfa`] and embeds it with `include_bytes!`. [`lexer_for`]
+//! picks the right DFA for a file extension and deserializes it into a ready
+//! to use [`Lexer`].
+
+use wisent::lexer::{Dfa, Lexer};
+
+include!(concat!(env!("OUT_DIR"), "/assign_grammars.in"));
+
+/// Builds a [`Lexer`] for files with the given extension, if a grammar is
+/// registered for it.
+pub fn lexer_for(extension: &str) -> Option<Lexer> {
+    let bytes = assign_dfas(extension);
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(Lexer::new(Dfa::from_bytes(bytes)))
+    }
+}
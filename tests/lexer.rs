@@ -0,0 +1,89 @@
+//
+// Directory-driven regression tests for the generated lexer DFAs.
+//
+// Every input file under `tests/lexer/ok` and `tests/lexer/err` is tokenized
+// with a `Lexer` built from the `simple` grammar, and the resulting token
+// kinds and spans are dumped to a deterministic text format that is compared
+// against a committed `.tokens` expectation file sitting next to the input.
+// Inputs under `ok/` must tokenize without lexical errors, inputs under `err/`
+// must contain at least one. The layout follows rust-analyzer's `dir_tests`.
+//
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use wisent::grammar::Grammar;
+use wisent::lexer::{Dfa, Lexer, TokenKind};
+
+/// Serializes a token stream into the deterministic `<KIND> <start>..<end>`
+/// format used by the `.tokens` expectation files.
+fn dump(tokens: impl IntoIterator<Item = (TokenKind, usize, usize)>) -> String {
+    let mut out = String::new();
+    for (kind, start, end) in tokens {
+        if kind.is_error() {
+            out.push_str("ERROR");
+        } else {
+            out.push_str(&kind.to_string());
+        }
+        out.push_str(&format!(" {}..{}\n", start, end));
+    }
+    out
+}
+
+/// Collects every regular file in `dir` that is not a `.tokens` expectation.
+fn inputs(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file() && path.extension().is_none_or(|e| e != "tokens"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Tokenizes every input in `dir`, compares the dump against the sibling
+/// `.tokens` file, and asserts `errors_expected` matches what was produced.
+fn dir_tests(lexer: &Lexer, dir: &Path, errors_expected: bool) {
+    for input in inputs(dir) {
+        let source = fs::read_to_string(&input)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", input.display(), e));
+        let tokens: Vec<_> = lexer.tokenize(&source).collect();
+        let has_errors = tokens.iter().any(|(kind, _, _)| kind.is_error());
+        assert_eq!(
+            has_errors,
+            errors_expected,
+            "{}: expected lexical errors to be {}",
+            input.display(),
+            errors_expected
+        );
+        let expected_path = input.with_extension("tokens");
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", expected_path.display(), e));
+        assert_eq!(
+            dump(tokens),
+            expected,
+            "token mismatch for {}",
+            input.display()
+        );
+    }
+}
+
+#[test]
+fn lexer_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lexer");
+    let grammar = Grammar::parse_grammar(dir.join("simple.g4").to_str().unwrap()).unwrap();
+    let lexer = Lexer::new(Dfa::new(&grammar).minimize());
+    dir_tests(&lexer, &dir.join("ok"), false);
+    dir_tests(&lexer, &dir.join("err"), true);
+}
+
+/// `main.g4` imports `base.g4` and overrides its `INT` rule to also accept
+/// hex literals; the golden file only tokenizes cleanly if the importer's
+/// rule (not the imported one) actually won.
+#[test]
+fn lexer_import_override() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lexer/import");
+    let grammar = Grammar::parse_grammar(dir.join("main.g4").to_str().unwrap()).unwrap();
+    let lexer = Lexer::new(Dfa::new(&grammar).minimize());
+    dir_tests(&lexer, &dir.join("ok"), false);
+}
@@ -0,0 +1,29 @@
+//! Error type shared across the crate.
+
+use std::fmt;
+
+/// Error raised while reading or parsing a grammar.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The grammar file could not be read.
+    Io(std::io::Error),
+    /// The grammar source was syntactically invalid.
+    Syntax(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "I/O error: {}", e),
+            ParseError::Syntax(msg) => write!(f, "syntax error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<std::io::Error> for ParseError {
+    fn from(err: std::io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
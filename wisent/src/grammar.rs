@@ -0,0 +1,553 @@
+//! ANTLR-style grammar parsing.
+//!
+//! [`Grammar::parse_grammar`] parses a `*.g4` file and resolves its `import`
+//! statements, locating each referenced grammar next to the importing file,
+//! parsing it recursively and merging the rule sets. Rules defined in the
+//! importing grammar override imported rules of the same name.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::ParseError;
+use crate::lexer::ByteSet;
+
+/// A compiled regular expression over bytes, the body of a lexer rule.
+pub(crate) enum Ast {
+    Epsilon,
+    Byte(u8),
+    Class(Box<ByteSet>),
+    Concat(Vec<Ast>),
+    Alt(Vec<Ast>),
+    Star(Box<Ast>),
+    Plus(Box<Ast>),
+    Opt(Box<Ast>),
+    Ref(String),
+}
+
+/// A single grammar rule.
+pub(crate) struct Rule {
+    pub name: String,
+    pub ast: Ast,
+    /// `true` when the rule is a lexer token (uppercase name, not a fragment).
+    pub token: bool,
+    /// The Javadoc-style `/** ... */` comment immediately preceding the rule,
+    /// if any.
+    pub doc: Option<String>,
+}
+
+/// A parsed grammar together with the files that contributed to it.
+pub struct Grammar {
+    /// The grammar name from its declaration.
+    pub name: String,
+    pub(crate) rules: Vec<Rule>,
+    sources: Vec<PathBuf>,
+    doc: Option<String>,
+    rule_docs: HashMap<String, String>,
+}
+
+impl Grammar {
+    /// Parses the grammar at `path`, recursively resolving `import` statements
+    /// relative to it and merging the imported rules (importer wins on name
+    /// clashes).
+    pub fn parse_grammar(path: &str) -> Result<Grammar, ParseError> {
+        let path = PathBuf::from(path);
+        let mut sources = Vec::new();
+        let mut rules: Vec<Rule> = Vec::new();
+        let mut rule_docs = HashMap::new();
+        let mut names: HashSet<String> = HashSet::new();
+        let mut visited: HashSet<PathBuf> = HashSet::new();
+        let (name, doc) = collect(
+            &path,
+            &mut sources,
+            &mut rules,
+            &mut rule_docs,
+            &mut names,
+            &mut visited,
+        )?;
+        Ok(Grammar {
+            name,
+            rules,
+            sources,
+            doc,
+            rule_docs,
+        })
+    }
+
+    /// Every file that contributed to this grammar, the main file first,
+    /// followed by transitively imported files.
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    /// The Javadoc-style comment preceding the grammar's own `grammar`
+    /// declaration, if any. Doc comments on imported grammars are not
+    /// surfaced here; only the entry file's is.
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// The Javadoc-style comment preceding each rule declaration, keyed by
+    /// rule name. Only rules that actually have one are present.
+    pub fn rule_docs(&self) -> &HashMap<String, String> {
+        &self.rule_docs
+    }
+}
+
+/// Parses `path`, appends its rules (importer-wins) and recurses into its
+/// imports. Returns the grammar name and doc comment of `path`.
+fn collect(
+    path: &Path,
+    sources: &mut Vec<PathBuf>,
+    rules: &mut Vec<Rule>,
+    rule_docs: &mut HashMap<String, String>,
+    names: &mut HashSet<String>,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<(String, Option<String>), ParseError> {
+    visited.insert(path.to_path_buf());
+    sources.push(path.to_path_buf());
+    let text = std::fs::read_to_string(path)?;
+    let parsed = parse_file(&text)?;
+    // The importer's rules are added first, so later imports cannot override
+    // an already-seen name.
+    for rule in parsed.rules {
+        if names.insert(rule.name.clone()) {
+            if let Some(doc) = &rule.doc {
+                rule_docs.insert(rule.name.clone(), doc.clone());
+            }
+            rules.push(rule);
+        }
+    }
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for import in parsed.imports {
+        let imported = dir.join(format!("{}.g4", import));
+        if !visited.contains(&imported) {
+            collect(&imported, sources, rules, rule_docs, names, visited)?;
+        }
+    }
+    Ok((parsed.name, parsed.doc))
+}
+
+struct Parsed {
+    name: String,
+    doc: Option<String>,
+    imports: Vec<String>,
+    rules: Vec<Rule>,
+}
+
+fn parse_file(text: &str) -> Result<Parsed, ParseError> {
+    let mut p = Cursor::new(text.as_bytes());
+    // A `/** ... */` comment right before the (optional `lexer`/`parser`
+    // qualified) `grammar` declaration is the grammar's own doc comment.
+    let doc = p.skip_trivia();
+    let mut kw = p.read_ident()?;
+    if kw == "lexer" || kw == "parser" {
+        p.skip_trivia();
+        kw = p.read_ident()?;
+    }
+    if kw != "grammar" {
+        return Err(ParseError::Syntax(format!(
+            "expected `grammar` declaration, found `{}`",
+            kw
+        )));
+    }
+    p.skip_trivia();
+    let name = p.read_ident()?;
+    p.skip_trivia();
+    p.expect(b';')?;
+
+    let mut imports = Vec::new();
+    let mut rules = Vec::new();
+    loop {
+        // A `/** ... */` comment right before a rule is that rule's doc
+        // comment; it's discarded for `import` statements.
+        let rule_doc = p.skip_trivia();
+        if p.at_end() {
+            break;
+        }
+        let word = p.read_ident()?;
+        if word == "import" {
+            loop {
+                p.skip_trivia();
+                imports.push(p.read_ident()?);
+                p.skip_trivia();
+                if p.peek() == Some(b',') {
+                    p.bump();
+                    continue;
+                }
+                p.expect(b';')?;
+                break;
+            }
+        } else if word == "fragment" {
+            p.skip_trivia();
+            let rname = p.read_ident()?;
+            let ast = p.parse_rule_body()?;
+            rules.push(Rule {
+                token: false,
+                name: rname,
+                ast,
+                doc: rule_doc,
+            });
+        } else {
+            let ast = p.parse_rule_body()?;
+            let token = word.chars().next().is_some_and(|c| c.is_ascii_uppercase());
+            rules.push(Rule {
+                name: word,
+                ast,
+                token,
+                doc: rule_doc,
+            });
+        }
+    }
+    Ok(Parsed {
+        name,
+        doc,
+        imports,
+        rules,
+    })
+}
+
+/// Strips the common Javadoc-style leading `*` and indentation from each line
+/// of a `/** ... */` comment body, leaving the prose behind.
+fn clean_doc_comment(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').map_or(line, str::trim)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// A byte cursor over the grammar source with the recursive-descent parser for
+/// rule bodies.
+struct Cursor<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(src: &'a [u8]) -> Self {
+        Cursor { src, pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn peek2(&self) -> Option<u8> {
+        self.src.get(self.pos + 1).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek();
+        if b.is_some() {
+            self.pos += 1;
+        }
+        b
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(ParseError::Syntax(format!(
+                "expected `{}`",
+                b as char
+            )))
+        }
+    }
+
+    /// Skips whitespace and comments (`//`, `/* */` and `/** */`), returning
+    /// the cleaned body of the last `/** ... */` Javadoc-style comment
+    /// skipped, if any. Callers that care about doc comments (grammar and
+    /// rule declarations) use the return value; the rest ignore it.
+    fn skip_trivia(&mut self) -> Option<String> {
+        let mut doc = None;
+        loop {
+            match self.peek() {
+                Some(b) if b.is_ascii_whitespace() => {
+                    self.pos += 1;
+                }
+                Some(b'/') if self.peek2() == Some(b'/') => {
+                    while let Some(b) = self.peek() {
+                        self.pos += 1;
+                        if b == b'\n' {
+                            break;
+                        }
+                    }
+                }
+                Some(b'/') if self.peek2() == Some(b'*') => {
+                    // `/**` (but not the empty `/**/`) marks a doc comment.
+                    let is_doc = self.src.get(self.pos + 2) == Some(&b'*')
+                        && self.src.get(self.pos + 3) != Some(&b'/');
+                    self.pos += 2;
+                    let body_start = if is_doc { self.pos + 1 } else { self.pos };
+                    while self.pos < self.src.len() {
+                        if self.src[self.pos] == b'*' && self.peek2() == Some(b'/') {
+                            if is_doc {
+                                let text =
+                                    String::from_utf8_lossy(&self.src[body_start..self.pos]);
+                                doc = Some(clean_doc_comment(&text));
+                            }
+                            self.pos += 2;
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        doc
+    }
+
+    fn read_ident(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            if b == b'_' || b.is_ascii_alphanumeric() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(ParseError::Syntax("expected identifier".to_string()));
+        }
+        Ok(String::from_utf8_lossy(&self.src[start..self.pos]).into_owned())
+    }
+
+    /// Parses `: alternation ;` and returns the rule body.
+    fn parse_rule_body(&mut self) -> Result<Ast, ParseError> {
+        self.skip_trivia();
+        self.expect(b':')?;
+        let ast = self.parse_alt()?;
+        self.skip_trivia();
+        self.expect(b';')?;
+        Ok(ast)
+    }
+
+    fn parse_alt(&mut self) -> Result<Ast, ParseError> {
+        let mut alts = vec![self.parse_concat()?];
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(b'|') {
+                self.bump();
+                alts.push(self.parse_concat()?);
+            } else {
+                break;
+            }
+        }
+        if alts.len() == 1 {
+            Ok(alts.pop().unwrap())
+        } else {
+            Ok(Ast::Alt(alts))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Ast, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None | Some(b'|') | Some(b')') | Some(b';') => break,
+                // `-> skip`, `-> channel(HIDDEN)` and similar lexer commands run
+                // to the end of the rule; ignore them.
+                Some(b'-') if self.peek2() == Some(b'>') => {
+                    while let Some(b) = self.peek() {
+                        if b == b';' {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                    break;
+                }
+                // Embedded actions `{ ... }`.
+                Some(b'{') => {
+                    let mut depth = 0;
+                    while let Some(b) = self.bump() {
+                        match b {
+                            b'{' => depth += 1,
+                            b'}' => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => items.push(self.parse_repeat()?),
+            }
+        }
+        if items.len() == 1 {
+            Ok(items.pop().unwrap())
+        } else if items.is_empty() {
+            Ok(Ast::Epsilon)
+        } else {
+            Ok(Ast::Concat(items))
+        }
+    }
+
+    fn parse_repeat(&mut self) -> Result<Ast, ParseError> {
+        let atom = self.parse_atom()?;
+        match self.peek() {
+            Some(b'*') => {
+                self.bump();
+                self.skip_suffix_modifier();
+                Ok(Ast::Star(Box::new(atom)))
+            }
+            Some(b'+') => {
+                self.bump();
+                self.skip_suffix_modifier();
+                Ok(Ast::Plus(Box::new(atom)))
+            }
+            Some(b'?') => {
+                self.bump();
+                self.skip_suffix_modifier();
+                Ok(Ast::Opt(Box::new(atom)))
+            }
+            _ => Ok(atom),
+        }
+    }
+
+    /// Consumes a trailing non-greedy `?` (e.g. `*?`), which does not affect
+    /// the recognized language.
+    fn skip_suffix_modifier(&mut self) {
+        if self.peek() == Some(b'?') {
+            self.bump();
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Ast, ParseError> {
+        self.skip_trivia();
+        match self.peek() {
+            Some(b'(') => {
+                self.bump();
+                let ast = self.parse_alt()?;
+                self.skip_trivia();
+                self.expect(b')')?;
+                Ok(ast)
+            }
+            Some(b'\'') => self.parse_string(),
+            Some(b'[') => self.parse_class(),
+            Some(b'.') => {
+                self.bump();
+                Ok(Ast::Class(Box::new(ByteSet::full())))
+            }
+            Some(b) if b == b'_' || b.is_ascii_alphabetic() => {
+                let name = self.read_ident()?;
+                Ok(Ast::Ref(name))
+            }
+            other => Err(ParseError::Syntax(format!(
+                "unexpected byte `{}` in rule body",
+                other.map(|b| b as char).unwrap_or('∅')
+            ))),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<Ast, ParseError> {
+        self.expect(b'\'')?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(ParseError::Syntax("unterminated string literal".to_string())),
+                Some(b'\'') => {
+                    self.bump();
+                    break;
+                }
+                Some(b'\\') => {
+                    self.bump();
+                    self.read_escape(&mut bytes);
+                }
+                Some(b) => {
+                    self.bump();
+                    bytes.push(b);
+                }
+            }
+        }
+        if bytes.len() == 1 {
+            Ok(Ast::Byte(bytes[0]))
+        } else {
+            Ok(Ast::Concat(bytes.into_iter().map(Ast::Byte).collect()))
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Ast, ParseError> {
+        self.expect(b'[')?;
+        let mut set = ByteSet::new();
+        let negated = if self.peek() == Some(b'^') {
+            self.bump();
+            true
+        } else {
+            false
+        };
+        while let Some(b) = self.peek() {
+            if b == b']' {
+                self.bump();
+                if negated {
+                    set.negate();
+                }
+                return Ok(Ast::Class(Box::new(set)));
+            }
+            let lo = self.read_class_byte();
+            if self.peek() == Some(b'-') && self.peek2() != Some(b']') {
+                self.bump();
+                let hi = self.read_class_byte();
+                set.insert_range(lo, hi);
+            } else {
+                set.insert(lo);
+            }
+        }
+        Err(ParseError::Syntax("unterminated character class".to_string()))
+    }
+
+    /// Reads a single (possibly escaped) byte inside a character class.
+    fn read_class_byte(&mut self) -> u8 {
+        match self.bump() {
+            Some(b'\\') => {
+                let mut scratch = Vec::new();
+                self.read_escape(&mut scratch);
+                scratch.first().copied().unwrap_or(b'\\')
+            }
+            Some(b) => b,
+            None => 0,
+        }
+    }
+
+    /// Decodes the escape sequence following a backslash into `out`.
+    fn read_escape(&mut self, out: &mut Vec<u8>) {
+        match self.bump() {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'f') => out.push(0x0c),
+            Some(b'b') => out.push(0x08),
+            Some(b'u') => {
+                let mut value: u32 = 0;
+                for _ in 0..4 {
+                    match self.peek().and_then(|b| (b as char).to_digit(16)) {
+                        Some(d) => {
+                            value = value * 16 + d;
+                            self.bump();
+                        }
+                        None => break,
+                    }
+                }
+                if let Some(c) = char::from_u32(value) {
+                    let mut buf = [0u8; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+            Some(b) => out.push(b),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,610 @@
+//! Lexer DFA construction, serialization and the streaming tokenizer.
+//!
+//! [`Dfa::new`] turns a [`Grammar`] into a deterministic finite automaton via
+//! Thompson construction followed by the subset construction. The automaton can
+//! be serialized with [`Dfa::as_bytes`] (for embedding with `include_bytes!`)
+//! and read back with [`Dfa::from_bytes`]. [`Lexer`] runs the automaton over
+//! source text with longest-match semantics.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::grammar::{Ast, Grammar};
+
+/// A set of bytes, used as the label of an automaton edge and as a compiled
+/// character class.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct ByteSet {
+    bits: [bool; 256],
+}
+
+impl ByteSet {
+    pub(crate) fn new() -> Self {
+        ByteSet { bits: [false; 256] }
+    }
+
+    /// A class matching every byte (the `.` wildcard).
+    pub(crate) fn full() -> Self {
+        ByteSet { bits: [true; 256] }
+    }
+
+    pub(crate) fn insert(&mut self, b: u8) {
+        self.bits[b as usize] = true;
+    }
+
+    pub(crate) fn insert_range(&mut self, lo: u8, hi: u8) {
+        for b in lo..=hi {
+            self.bits[b as usize] = true;
+        }
+    }
+
+    pub(crate) fn negate(&mut self) {
+        for b in self.bits.iter_mut() {
+            *b = !*b;
+        }
+    }
+
+    pub(crate) fn contains(&self, b: u8) -> bool {
+        self.bits[b as usize]
+    }
+}
+
+// --- Thompson NFA -----------------------------------------------------------
+
+struct NfaState {
+    eps: Vec<usize>,
+    edges: Vec<(ByteSet, usize)>,
+}
+
+struct Nfa {
+    states: Vec<NfaState>,
+    /// Maps an accepting NFA state to the priority (declaration order) and name
+    /// of the token rule it recognizes.
+    accepts: HashMap<usize, (usize, String)>,
+    start: usize,
+}
+
+struct Fragment {
+    start: usize,
+    accept: usize,
+}
+
+impl Nfa {
+    fn new() -> Self {
+        Nfa {
+            states: Vec::new(),
+            accepts: HashMap::new(),
+            start: 0,
+        }
+    }
+
+    fn push_state(&mut self) -> usize {
+        self.states.push(NfaState {
+            eps: Vec::new(),
+            edges: Vec::new(),
+        });
+        self.states.len() - 1
+    }
+
+    fn add_eps(&mut self, from: usize, to: usize) {
+        self.states[from].eps.push(to);
+    }
+
+    fn add_edge(&mut self, from: usize, set: ByteSet, to: usize) {
+        self.states[from].edges.push((set, to));
+    }
+
+    /// Builds an NFA fragment for `ast`, inlining rule references through
+    /// `rules`. `stack` guards against recursive references.
+    fn build(
+        &mut self,
+        ast: &Ast,
+        rules: &HashMap<String, &Ast>,
+        stack: &mut Vec<String>,
+    ) -> Fragment {
+        match ast {
+            Ast::Epsilon => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                self.add_eps(start, accept);
+                Fragment { start, accept }
+            }
+            Ast::Byte(b) => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                let mut set = ByteSet::new();
+                set.insert(*b);
+                self.add_edge(start, set, accept);
+                Fragment { start, accept }
+            }
+            Ast::Class(set) => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                self.add_edge(start, (**set).clone(), accept);
+                Fragment { start, accept }
+            }
+            Ast::Concat(items) => {
+                if items.is_empty() {
+                    return self.build(&Ast::Epsilon, rules, stack);
+                }
+                let first = self.build(&items[0], rules, stack);
+                let mut accept = first.accept;
+                for item in &items[1..] {
+                    let frag = self.build(item, rules, stack);
+                    self.add_eps(accept, frag.start);
+                    accept = frag.accept;
+                }
+                Fragment {
+                    start: first.start,
+                    accept,
+                }
+            }
+            Ast::Alt(alts) => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                for alt in alts {
+                    let frag = self.build(alt, rules, stack);
+                    self.add_eps(start, frag.start);
+                    self.add_eps(frag.accept, accept);
+                }
+                Fragment { start, accept }
+            }
+            Ast::Star(inner) => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                let frag = self.build(inner, rules, stack);
+                self.add_eps(start, frag.start);
+                self.add_eps(start, accept);
+                self.add_eps(frag.accept, frag.start);
+                self.add_eps(frag.accept, accept);
+                Fragment { start, accept }
+            }
+            Ast::Plus(inner) => {
+                let frag = self.build(inner, rules, stack);
+                let accept = self.push_state();
+                self.add_eps(frag.accept, frag.start);
+                self.add_eps(frag.accept, accept);
+                Fragment {
+                    start: frag.start,
+                    accept,
+                }
+            }
+            Ast::Opt(inner) => {
+                let start = self.push_state();
+                let accept = self.push_state();
+                let frag = self.build(inner, rules, stack);
+                self.add_eps(start, frag.start);
+                self.add_eps(start, accept);
+                self.add_eps(frag.accept, accept);
+                Fragment { start, accept }
+            }
+            Ast::Ref(name) => match rules.get(name) {
+                Some(inner) if !stack.iter().any(|n| n == name) => {
+                    stack.push(name.clone());
+                    let frag = self.build(inner, rules, stack);
+                    stack.pop();
+                    frag
+                }
+                // Missing or recursive reference: treat as the empty match so
+                // construction stays total.
+                _ => self.build(&Ast::Epsilon, rules, stack),
+            },
+        }
+    }
+}
+
+// --- DFA --------------------------------------------------------------------
+
+/// A deterministic finite automaton recognizing a grammar's lexer rules.
+pub struct Dfa {
+    /// Per-state transition table, keyed by input byte.
+    pub(crate) trans: Vec<BTreeMap<u8, usize>>,
+    /// Per-state accepting token rule, if any.
+    pub(crate) accept: Vec<Option<String>>,
+    pub(crate) start: usize,
+}
+
+impl Dfa {
+    /// Compiles the lexer rules of `grammar` into a DFA.
+    pub fn new(grammar: &Grammar) -> Dfa {
+        // Collect every rule body so references (including fragments) resolve.
+        let rule_asts: HashMap<String, &Ast> = grammar
+            .rules
+            .iter()
+            .map(|r| (r.name.clone(), &r.ast))
+            .collect();
+
+        let mut nfa = Nfa::new();
+        let global_start = nfa.push_state();
+        nfa.start = global_start;
+        // Only token rules (uppercase, non-fragment) contribute accept states;
+        // their declaration order is their priority.
+        for (priority, rule) in grammar.rules.iter().filter(|r| r.token).enumerate() {
+            let mut stack = Vec::new();
+            let frag = nfa.build(&rule.ast, &rule_asts, &mut stack);
+            nfa.add_eps(global_start, frag.start);
+            nfa.accepts
+                .insert(frag.accept, (priority, rule.name.clone()));
+        }
+
+        subset_construction(&nfa)
+    }
+
+    /// Merges equivalent states with Hopcroft's partition-refinement
+    /// algorithm, shrinking the transition table without changing which
+    /// token (if any) is recognized for any input.
+    pub fn minimize(self) -> Dfa {
+        let n = self.trans.len();
+        // An extra trap state gives every state a transition on every byte,
+        // which the algorithm requires; states that never reach an accept
+        // are equivalent to it and get folded away below.
+        let dead = n;
+        let total = n + 1;
+        let mut goto_table: Vec<[usize; 256]> = vec![[dead; 256]; total];
+        for (state, row) in self.trans.iter().enumerate() {
+            for (&b, &target) in row {
+                goto_table[state][b as usize] = target;
+            }
+        }
+
+        // Initial partition: states sharing the same accept label. States
+        // accepting different rules must never merge, so each named label
+        // gets its own block; non-accepting states (and the trap) share one.
+        let mut groups: HashMap<Option<String>, Vec<usize>> = HashMap::new();
+        for (state, label) in self.accept.iter().enumerate() {
+            groups.entry(label.clone()).or_default().push(state);
+        }
+        groups.entry(None).or_default().push(dead);
+        let mut partition: Vec<Vec<usize>> = groups.into_values().collect();
+        let mut worklist: Vec<Vec<usize>> = partition.clone();
+
+        while let Some(splitter) = worklist.pop() {
+            let splitter: HashSet<usize> = splitter.into_iter().collect();
+            for symbol in 0u16..=255 {
+                let symbol = symbol as u8;
+                // States with a transition on `symbol` landing inside the splitter.
+                let reaches_splitter: HashSet<usize> = (0..total)
+                    .filter(|&s| splitter.contains(&goto_table[s][symbol as usize]))
+                    .collect();
+                if reaches_splitter.is_empty() {
+                    continue;
+                }
+                let mut refined = Vec::with_capacity(partition.len());
+                for block in &partition {
+                    let (inter, diff): (Vec<usize>, Vec<usize>) = block
+                        .iter()
+                        .copied()
+                        .partition(|s| reaches_splitter.contains(s));
+                    if inter.is_empty() || diff.is_empty() {
+                        refined.push(block.clone());
+                        continue;
+                    }
+                    if let Some(pos) = worklist.iter().position(|w| w == block) {
+                        worklist.remove(pos);
+                        worklist.push(inter.clone());
+                        worklist.push(diff.clone());
+                    } else if inter.len() <= diff.len() {
+                        worklist.push(inter.clone());
+                    } else {
+                        worklist.push(diff.clone());
+                    }
+                    refined.push(inter);
+                    refined.push(diff);
+                }
+                partition = refined;
+            }
+        }
+
+        let mut block_of = vec![0usize; total];
+        for (block_idx, block) in partition.iter().enumerate() {
+            for &s in block {
+                block_of[s] = block_idx;
+            }
+        }
+        // The block holding the trap state (with every state that can never
+        // reach an accept) isn't a real output state; transitions into it are
+        // simply omitted, same as in the original sparse table.
+        let live_blocks: Vec<usize> = partition
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| !block.contains(&dead))
+            .map(|(idx, _)| idx)
+            .collect();
+        let new_id: HashMap<usize, usize> = live_blocks
+            .iter()
+            .enumerate()
+            .map(|(new_idx, &block_idx)| (block_idx, new_idx))
+            .collect();
+        // A start state that can never reach an accept recognizes nothing.
+        let Some(&start) = new_id.get(&block_of[self.start]) else {
+            return Dfa {
+                trans: vec![BTreeMap::new()],
+                accept: vec![None],
+                start: 0,
+            };
+        };
+
+        let mut trans = vec![BTreeMap::new(); live_blocks.len()];
+        let mut accept = vec![None; live_blocks.len()];
+        for (new_idx, &block_idx) in live_blocks.iter().enumerate() {
+            let representative = partition[block_idx]
+                .iter()
+                .copied()
+                .find(|&s| s != dead)
+                .expect("live block contains a non-trap state");
+            accept[new_idx] = self.accept[representative].clone();
+            for symbol in 0u16..=255 {
+                let symbol = symbol as u8;
+                let target_block = block_of[goto_table[representative][symbol as usize]];
+                if let Some(&target) = new_id.get(&target_block) {
+                    trans[new_idx].insert(symbol, target);
+                }
+            }
+        }
+
+        Dfa {
+            trans,
+            accept,
+            start,
+        }
+    }
+
+    /// Serializes the automaton into a compact byte buffer suitable for
+    /// `include_bytes!`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_u32(&mut out, self.trans.len() as u32);
+        write_u32(&mut out, self.start as u32);
+        for state in 0..self.trans.len() {
+            match &self.accept[state] {
+                Some(name) => {
+                    out.push(1);
+                    let bytes = name.as_bytes();
+                    write_u16(&mut out, bytes.len() as u16);
+                    out.extend_from_slice(bytes);
+                }
+                None => out.push(0),
+            }
+            let trans = &self.trans[state];
+            write_u16(&mut out, trans.len() as u16);
+            for (symbol, target) in trans {
+                out.push(*symbol);
+                write_u32(&mut out, *target as u32);
+            }
+        }
+        out
+    }
+
+    /// Reconstructs an automaton produced by [`Dfa::as_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Dfa {
+        let mut cur = 0;
+        let num_states = read_u32(bytes, &mut cur) as usize;
+        let start = read_u32(bytes, &mut cur) as usize;
+        let mut trans = Vec::with_capacity(num_states);
+        let mut accept = Vec::with_capacity(num_states);
+        for _ in 0..num_states {
+            let has_accept = bytes[cur];
+            cur += 1;
+            if has_accept == 1 {
+                let len = read_u16(bytes, &mut cur) as usize;
+                let name = String::from_utf8_lossy(&bytes[cur..cur + len]).into_owned();
+                cur += len;
+                accept.push(Some(name));
+            } else {
+                accept.push(None);
+            }
+            let num_trans = read_u16(bytes, &mut cur) as usize;
+            let mut map = BTreeMap::new();
+            for _ in 0..num_trans {
+                let symbol = bytes[cur];
+                cur += 1;
+                let target = read_u32(bytes, &mut cur) as usize;
+                map.insert(symbol, target);
+            }
+            trans.push(map);
+        }
+        Dfa {
+            trans,
+            accept,
+            start,
+        }
+    }
+}
+
+fn subset_construction(nfa: &Nfa) -> Dfa {
+    let start_set = epsilon_closure(nfa, &[nfa.start]);
+    let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut order: Vec<Vec<usize>> = Vec::new();
+    let start_key: Vec<usize> = start_set.iter().copied().collect();
+    ids.insert(start_key.clone(), 0);
+    order.push(start_key);
+
+    let mut trans: Vec<BTreeMap<u8, usize>> = Vec::new();
+    let mut accept: Vec<Option<String>> = Vec::new();
+    let mut i = 0;
+    while i < order.len() {
+        let set = order[i].clone();
+        // Resolve the accepting rule with the highest priority (lowest index).
+        let label = set
+            .iter()
+            .filter_map(|s| nfa.accepts.get(s))
+            .min_by_key(|(priority, _)| *priority)
+            .map(|(_, name)| name.clone());
+        accept.push(label);
+
+        let mut row = BTreeMap::new();
+        for b in 0u16..=255 {
+            let symbol = b as u8;
+            let moved = move_on(nfa, &set, symbol);
+            if moved.is_empty() {
+                continue;
+            }
+            let closure = epsilon_closure(nfa, &moved);
+            let key: Vec<usize> = closure.iter().copied().collect();
+            let id = match ids.get(&key) {
+                Some(id) => *id,
+                None => {
+                    let id = order.len();
+                    ids.insert(key.clone(), id);
+                    order.push(key);
+                    id
+                }
+            };
+            row.insert(symbol, id);
+        }
+        trans.push(row);
+        i += 1;
+    }
+
+    Dfa {
+        trans,
+        accept,
+        start: 0,
+    }
+}
+
+fn epsilon_closure(nfa: &Nfa, states: &[usize]) -> BTreeSet<usize> {
+    let mut closure: BTreeSet<usize> = states.iter().copied().collect();
+    let mut stack: Vec<usize> = states.to_vec();
+    while let Some(s) = stack.pop() {
+        for &t in &nfa.states[s].eps {
+            if closure.insert(t) {
+                stack.push(t);
+            }
+        }
+    }
+    closure
+}
+
+fn move_on(nfa: &Nfa, states: &[usize], symbol: u8) -> Vec<usize> {
+    let mut out = Vec::new();
+    for &s in states {
+        for (set, target) in &nfa.states[s].edges {
+            if set.contains(symbol) {
+                out.push(*target);
+            }
+        }
+    }
+    out
+}
+
+// --- tokenizer --------------------------------------------------------------
+
+/// The kind of a lexed token: either a named grammar rule or a lexical error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A token recognized by the rule with the given name.
+    Rule(String),
+    /// An unrecognized byte that matched no rule.
+    Error,
+}
+
+impl TokenKind {
+    /// Returns `true` for the lexical-error token kind.
+    pub fn is_error(&self) -> bool {
+        matches!(self, TokenKind::Error)
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Rule(name) => f.write_str(name),
+            TokenKind::Error => f.write_str("ERROR"),
+        }
+    }
+}
+
+/// Runs a serialized [`Dfa`] over source text.
+pub struct Lexer {
+    dfa: Dfa,
+}
+
+impl Lexer {
+    /// Wraps a DFA so it can tokenize source text.
+    pub fn new(dfa: Dfa) -> Self {
+        Lexer { dfa }
+    }
+
+    /// Returns an iterator over `(TokenKind, start, end)` byte spans. Each
+    /// byte that matches no rule is surfaced as its own one-byte
+    /// [`TokenKind::Error`] token.
+    pub fn tokenize<'a>(&'a self, input: &'a str) -> Tokens<'a> {
+        Tokens {
+            dfa: &self.dfa,
+            input: input.as_bytes(),
+            pos: 0,
+        }
+    }
+}
+
+/// Iterator returned by [`Lexer::tokenize`].
+pub struct Tokens<'a> {
+    dfa: &'a Dfa,
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = (TokenKind, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        let start = self.pos;
+        let mut state = self.dfa.start;
+        // Longest match: remember the last accepting state and the position
+        // right after it while advancing, and fall back to it on a dead state.
+        let mut last_accept: Option<(usize, String)> = None;
+        let mut i = start;
+        while i < self.input.len() {
+            match self.dfa.trans[state].get(&self.input[i]) {
+                Some(&next) => {
+                    state = next;
+                    i += 1;
+                    if let Some(name) = &self.dfa.accept[state] {
+                        last_accept = Some((i, name.clone()));
+                    }
+                }
+                None => break,
+            }
+        }
+        match last_accept {
+            Some((end, name)) => {
+                self.pos = end;
+                Some((TokenKind::Rule(name), start, end))
+            }
+            None => {
+                // No rule matched: emit a single-byte error and resynchronize.
+                self.pos = start + 1;
+                Some((TokenKind::Error, start, start + 1))
+            }
+        }
+    }
+}
+
+fn write_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_u16(bytes: &[u8], cur: &mut usize) -> u16 {
+    let value = u16::from_le_bytes([bytes[*cur], bytes[*cur + 1]]);
+    *cur += 2;
+    value
+}
+
+fn read_u32(bytes: &[u8], cur: &mut usize) -> u32 {
+    let value = u32::from_le_bytes([
+        bytes[*cur],
+        bytes[*cur + 1],
+        bytes[*cur + 2],
+        bytes[*cur + 3],
+    ]);
+    *cur += 4;
+    value
+}
@@ -0,0 +1,6 @@
+//! `wisent` turns ANTLR-style lexer grammars into serializable DFAs and runs
+//! them over source text.
+
+pub mod error;
+pub mod grammar;
+pub mod lexer;